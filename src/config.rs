@@ -0,0 +1,273 @@
+//! On-disk configuration for `async-autotiling`.
+//!
+//! Settings can be provided on the command line (see [`crate::Args`]) or in a
+//! TOML file at `~/.config/async-autotiling/config.toml` (or the equivalent
+//! XDG/platform config dir). The file is optional; when present, its values
+//! are merged with the CLI arguments, with CLI-provided values always
+//! winning.
+
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::Args;
+
+/// Which tiling strategy `run_autotile` should use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum Mode {
+    /// React to the focused window's own aspect ratio (the original
+    /// behavior).
+    #[default]
+    AspectRatio,
+    /// Proactively re-split every container so windows on an output never
+    /// get narrower than that output's configured `min_window_width`. This
+    /// pass never consults `rules`: per-app overrides (`app_id`,
+    /// `window_class`, `title`) only apply to the `AspectRatio` path, so a
+    /// rule configured for an app has no effect while this mode is active.
+    MinWidth,
+}
+
+/// The layout a matching [`AppRule`] should force, or an instruction to
+/// leave autotiling out of it entirely.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLayout {
+    SplitH,
+    SplitV,
+    Tabbed,
+    Stacked,
+    /// Don't touch the layout at all for windows matching this rule.
+    Skip,
+}
+
+/// A single per-application override, as written in `config.toml`. A window
+/// matches when all of its populated fields match; unset fields are
+/// ignored, so a rule that sets none of `app_id`, `window_class` or `title`
+/// matches every window.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppRule {
+    /// Matches the Wayland `app_id` reported by sway.
+    pub app_id: Option<String>,
+    /// Matches the X11/i3 `window_properties.class`.
+    pub window_class: Option<String>,
+    /// Matches the window title against this regex.
+    pub title: Option<String>,
+    pub layout: RuleLayout,
+}
+
+impl AppRule {
+    /// Precompile `title` into a [`Regex`], so the hot path in
+    /// `run_autotile` never has to parse a pattern on a focus event.
+    /// Fails loudly (rather than the rule silently never matching) when the
+    /// pattern doesn't compile.
+    fn resolve(self) -> Result<ResolvedRule> {
+        let title = self
+            .title
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .with_context(|| format!("invalid `title` regex in rule: {:?}", self.title))?;
+
+        Ok(ResolvedRule {
+            app_id: self.app_id,
+            window_class: self.window_class,
+            title,
+            layout: self.layout,
+        })
+    }
+}
+
+/// An [`AppRule`] with its `title` pattern already compiled.
+#[derive(Debug, Clone)]
+pub struct ResolvedRule {
+    pub app_id: Option<String>,
+    pub window_class: Option<String>,
+    pub title: Option<Regex>,
+    pub layout: RuleLayout,
+}
+
+/// Shape of `config.toml` on disk. Every field is optional so that a user
+/// only needs to specify the settings they want to override.
+#[derive(Debug, Default, Deserialize)]
+struct FileConfig {
+    ratio: Option<f64>,
+    workspace: Option<Vec<String>>,
+    output_blocklist: Option<Vec<String>>,
+    /// Output name -> ratio, for monitors that need a different split
+    /// threshold than `ratio`. Falls back to `ratio` for unlisted outputs.
+    output_ratio: Option<HashMap<String, f64>>,
+    mode: Option<Mode>,
+    /// Output width (px) -> minimum acceptable window width (px). TOML
+    /// table keys are always strings, so widths are parsed on load.
+    min_window_width: Option<HashMap<String, u32>>,
+    /// Only consulted by `Mode::AspectRatio`; has no effect while
+    /// `mode = "min-width"` is active.
+    #[serde(default)]
+    rules: Vec<AppRule>,
+    /// Suppress all log output. Ideal for running as a silent background
+    /// service without having to pass `--quiet` on every invocation.
+    quiet: Option<bool>,
+}
+
+/// Fully resolved settings used by [`crate::run_autotile`], after merging
+/// the config file with CLI arguments.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub ratio: f64,
+    pub workspace: Vec<String>,
+    pub output_blocklist: Vec<String>,
+    pub output_ratio: HashMap<String, f64>,
+    pub mode: Mode,
+    pub min_window_width: HashMap<u32, u32>,
+    pub rules: Vec<ResolvedRule>,
+    pub quiet: bool,
+}
+
+impl Config {
+    /// Load `config.toml` (if it exists) and merge it with `args`, giving
+    /// CLI-provided values precedence over the file.
+    pub fn load(args: &Args) -> Result<Self> {
+        let file = Self::read_file()?.unwrap_or_default();
+        Self::merge(args, file)
+    }
+
+    /// Merge CLI args with a (possibly default, i.e. absent-file) parsed
+    /// `FileConfig`, giving CLI-provided values precedence. Fails if a
+    /// rule's `title` pattern doesn't compile as a regex.
+    fn merge(args: &Args, file: FileConfig) -> Result<Self> {
+        let rules = file
+            .rules
+            .into_iter()
+            .map(AppRule::resolve)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            ratio: args.ratio.or(file.ratio).unwrap_or(1.0),
+            workspace: if !args.workspace.is_empty() {
+                args.workspace.clone()
+            } else {
+                file.workspace.unwrap_or_default()
+            },
+            output_blocklist: file.output_blocklist.unwrap_or_default(),
+            output_ratio: file.output_ratio.unwrap_or_default(),
+            mode: args.mode.unwrap_or(file.mode.unwrap_or_default()),
+            min_window_width: if !args.min_window_width.is_empty() {
+                Self::parse_width_pairs(&args.min_window_width)
+            } else {
+                file.min_window_width
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|(width, min)| width.trim().parse().ok().map(|width| (width, min)))
+                    .collect()
+            },
+            rules,
+            quiet: args.quiet || file.quiet.unwrap_or(false),
+        })
+    }
+
+    /// Parse `WIDTH=MIN` pairs like `3840=700,2560=500` into a lookup map.
+    /// Entries that don't parse are silently skipped.
+    fn parse_width_pairs(pairs: &[String]) -> HashMap<u32, u32> {
+        pairs
+            .iter()
+            .filter_map(|pair| {
+                let (width, min) = pair.split_once('=')?;
+                Some((width.trim().parse().ok()?, min.trim().parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn read_file() -> Result<Option<FileConfig>> {
+        let Some(path) = Self::config_path() else {
+            return Ok(None);
+        };
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read config file at {}", path.display()))?;
+        let config: FileConfig = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse config file at {}", path.display()))?;
+
+        Ok(Some(config))
+    }
+
+    fn config_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "async-autotiling")
+            .map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args(argv: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("async-autotiling").chain(argv.iter().copied()))
+    }
+
+    #[test]
+    fn quiet_set_in_config_file_without_cli_flag() {
+        let file = FileConfig {
+            quiet: Some(true),
+            ..Default::default()
+        };
+        assert!(Config::merge(&args(&[]), file).unwrap().quiet);
+    }
+
+    #[test]
+    fn quiet_defaults_to_false() {
+        assert!(!Config::merge(&args(&[]), FileConfig::default()).unwrap().quiet);
+    }
+
+    #[test]
+    fn cli_quiet_flag_wins_over_an_absent_file_setting() {
+        assert!(Config::merge(&args(&["--quiet"]), FileConfig::default())
+            .unwrap()
+            .quiet);
+    }
+
+    #[test]
+    fn merge_rejects_an_invalid_title_regex() {
+        let file = FileConfig {
+            rules: vec![AppRule {
+                app_id: None,
+                window_class: None,
+                title: Some("(unclosed".into()),
+                layout: RuleLayout::Skip,
+            }],
+            ..Default::default()
+        };
+
+        assert!(Config::merge(&args(&[]), file).is_err());
+    }
+
+    #[test]
+    fn parse_width_pairs_skips_malformed_entries() {
+        let pairs = vec!["3840=700".to_string(), "not-a-pair".to_string()];
+        let parsed = Config::parse_width_pairs(&pairs);
+        assert_eq!(parsed.get(&3840), Some(&700));
+        assert_eq!(parsed.len(), 1);
+    }
+
+    #[test]
+    fn rule_layout_parses_sway_vocabulary_from_toml() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            [[rules]]
+            app_id = "firefox"
+            layout = "splith"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(file.rules[0].layout, RuleLayout::SplitH));
+    }
+}
@@ -1,8 +1,13 @@
 use anyhow::Result;
 use clap::Parser;
 use futures::StreamExt;
+use regex::Regex;
 use swayipc_async::{Connection, Event, EventType, Node, NodeLayout, NodeType, WindowChange};
 
+mod config;
+
+use config::{Config, Mode, RuleLayout};
+
 /// Automatically switch between horizontal/vertical split layout for sway/i3
 #[derive(Parser, Debug)]
 #[command(
@@ -20,8 +25,9 @@ struct Args {
     /// When `window_height > window_width / ratio`, the next split will be vertical.
     /// A value of 1.0 means any window taller than it is wide will trigger a vertical split.
     /// 1.618 (golden ratio) is a popular alternative.
-    #[arg(long, default_value_t = 1.0, value_name = "RATIO")]
-    ratio: f64,
+    /// Defaults to 1.0, or the value of `ratio` in config.toml if set there.
+    #[arg(long, value_name = "RATIO")]
+    ratio: Option<f64>,
 
     /// Restricts the script to run only on one or more specified workspaces.
     /// Provide a comma-separated list. If empty, the script will run on all workspaces.
@@ -34,6 +40,26 @@ struct Args {
     )]
     workspace: Vec<String>,
 
+    /// Selects the tiling strategy used on each focus event. `aspect-ratio`
+    /// (the default) reacts to the focused window's own dimensions;
+    /// `min-width` proactively re-splits every container on the tree so
+    /// windows on an output never get narrower than `min-window-width`
+    /// allows for it. Per-app `rules` in config.toml are only consulted by
+    /// `aspect-ratio`, so they have no effect while `min-width` is active.
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+
+    /// Output width (px) to minimum acceptable window width (px) mapping,
+    /// used by `--mode min-width`. Comma-separated `WIDTH=MIN` pairs.
+    /// Example: --min-window-width 3840=700,2560=500,1920=400
+    #[arg(
+        long,
+        value_delimiter = ',',
+        use_value_delimiter = true,
+        value_name = "WIDTH=MIN"
+    )]
+    min_window_width: Vec<String>,
+
     /// Run the logic once and exit immediately.
     /// Useful for scripting or one-off tests.
     #[arg(long, default_value_t = false)]
@@ -41,6 +67,8 @@ struct Args {
 
     /// Quiet mode, suppresses all log output.
     /// Ideal for running as a silent background service.
+    /// Also settable as `quiet` in config.toml; either source enabling it is
+    /// enough to go quiet.
     #[arg(long, short, default_value_t = false)]
     quiet: bool,
 }
@@ -69,12 +97,13 @@ impl Logger {
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    let logger = Logger::new(args.quiet);
+    let config = Config::load(&args)?;
+    let logger = Logger::new(config.quiet);
 
     let mut cmd_conn = Connection::new().await?;
 
     if args.once {
-        run_autotile(&mut cmd_conn, &args, &logger).await?;
+        run_autotile(&mut cmd_conn, &config, &logger).await?;
         return Ok(());
     }
 
@@ -87,7 +116,7 @@ async fn main() -> Result<()> {
         match events.next().await {
             Some(Ok(Event::Window(ev))) => {
                 if matches!(ev.change, WindowChange::Focus) {
-                    if let Err(e) = run_autotile(&mut cmd_conn, &args, &logger).await {
+                    if let Err(e) = run_autotile(&mut cmd_conn, &config, &logger).await {
                         logger.error(&format!("Error during auto-tiling: {}", e));
                     }
                 }
@@ -119,12 +148,12 @@ async fn main() -> Result<()> {
 }
 
 /// Core logic: determine and switch the layout.
-async fn run_autotile(conn: &mut Connection, args: &Args, logger: &Logger) -> Result<()> {
+async fn run_autotile(conn: &mut Connection, config: &Config, logger: &Logger) -> Result<()> {
     let tree = conn.get_tree().await?;
 
-    if !args.workspace.is_empty() {
+    if !config.workspace.is_empty() {
         if let Some(ws_name) = get_focused_workspace_name(conn).await? {
-            if !args.workspace.contains(&ws_name) {
+            if !config.workspace.contains(&ws_name) {
                 return Ok(());
             }
         } else {
@@ -132,49 +161,288 @@ async fn run_autotile(conn: &mut Connection, args: &Args, logger: &Logger) -> Re
         }
     }
 
+    if config.mode == Mode::MinWidth {
+        return apply_min_width_mode(conn, &tree, config, logger).await;
+    }
+
     // Find the parent of the focused node. This is more direct and efficient.
     // This closure means "find the node whose children contain the focused node".
     if let Some(parent) = tree.find_focused_as_ref(|n| n.nodes.iter().any(|child| child.focused)) {
         // Now, find the actual focused node within that parent.
         if let Some(focused_node) = parent.nodes.iter().find(|n| n.focused) {
-            if should_skip(focused_node) {
-                return Ok(());
+            match decide_focused_layout(&tree, focused_node, parent.layout, config) {
+                Some(FocusDecision::Rule(cmd)) => {
+                    conn.run_command(cmd).await?;
+                    logger.info(&format!("Applied rule -> layout forced to '{}'", cmd));
+                }
+                Some(FocusDecision::AspectRatio(cmd)) => {
+                    conn.run_command(cmd).await?;
+                    logger.info(&format!(
+                        "Focus changed -> Next split direction set to '{}'",
+                        cmd
+                    ));
+                }
+                None => {}
             }
+        }
+    }
+    Ok(())
+}
+
+/// What `run_autotile` should do about the focused node, and why (used only
+/// to pick the right log message).
+enum FocusDecision {
+    /// A per-app rule forced this layout.
+    Rule(&'static str),
+    /// The focused window's aspect ratio suggests this layout.
+    AspectRatio(&'static str),
+}
+
+/// Pure decision logic for the aspect-ratio path: given the focused node,
+/// its parent's current layout, and the resolved tree/config, decide what
+/// layout command (if any) `run_autotile` should issue. Returns `None` when
+/// nothing should change — the node should be skipped (fullscreen/tabbed
+/// ancestor, a blocklisted output, a `Skip` rule) or the container is
+/// already in the desired layout. Pulled out of `run_autotile` (which needs
+/// a live `Connection` and so can't be unit tested directly) so the
+/// blocklist-before-rule ordering has a test guarding it.
+fn decide_focused_layout(
+    tree: &Node,
+    focused_node: &Node,
+    parent_layout: NodeLayout,
+    config: &Config,
+) -> Option<FocusDecision> {
+    if should_skip(tree, focused_node) {
+        return None;
+    }
+
+    // Resolve the focused window's output and bail out before any other
+    // rule can fire if that output is blocklisted entirely.
+    let output = find_ancestor(tree, focused_node.id, |n| {
+        matches!(n.node_type, NodeType::Output)
+    });
+
+    if let Some(output_name) = output.and_then(|output| output.name.as_deref()) {
+        if config
+            .output_blocklist
+            .iter()
+            .any(|name| name == output_name)
+        {
+            return None;
+        }
+    }
 
-            let rect = focused_node.rect;
-            let height = rect.height as f64;
-            let width = rect.width as f64;
-
-            let new_layout = if height > width / args.ratio {
-                NodeLayout::SplitV
-            } else {
-                NodeLayout::SplitH
-            };
-
-            // If the parent's layout is already what we want, do nothing.
-            if new_layout != parent.layout {
-                let cmd = if new_layout == NodeLayout::SplitV {
-                    "splitv"
-                } else {
-                    "splith"
+    if let Some(rule) = matching_rule(focused_node, &config.rules) {
+        let (new_layout, cmd) = match rule.layout {
+            RuleLayout::Skip => return None,
+            RuleLayout::SplitH => (NodeLayout::SplitH, "splith"),
+            RuleLayout::SplitV => (NodeLayout::SplitV, "splitv"),
+            RuleLayout::Tabbed => (NodeLayout::Tabbed, "tabbed"),
+            RuleLayout::Stacked => (NodeLayout::Stacked, "stacked"),
+        };
+
+        // Don't re-issue a command that wouldn't change anything.
+        return (new_layout != parent_layout).then_some(FocusDecision::Rule(cmd));
+    }
+
+    let ratio = output
+        .and_then(|output| output.name.as_deref())
+        .and_then(|name| config.output_ratio.get(name))
+        .copied()
+        .unwrap_or(config.ratio);
+
+    let rect = focused_node.rect;
+    let height = rect.height as f64;
+    let width = rect.width as f64;
+
+    let new_layout = if height > width / ratio {
+        NodeLayout::SplitV
+    } else {
+        NodeLayout::SplitH
+    };
+    let cmd = if new_layout == NodeLayout::SplitV {
+        "splitv"
+    } else {
+        "splith"
+    };
+
+    // If the parent's layout is already what we want, do nothing.
+    (new_layout != parent_layout).then_some(FocusDecision::AspectRatio(cmd))
+}
+
+/// Whole-tree pass for `--mode min-width`: for every output, re-split each
+/// of its containers so the next window never ends up narrower than that
+/// output's configured minimum width. Restricted to `config.workspace`
+/// (when set), same as the aspect-ratio path.
+async fn apply_min_width_mode(
+    conn: &mut Connection,
+    tree: &Node,
+    config: &Config,
+    logger: &Logger,
+) -> Result<()> {
+    for output in &tree.nodes {
+        if !matches!(output.node_type, NodeType::Output) {
+            // The root's direct children should all be outputs; skip
+            // anything else rather than panicking on a live sway tree.
+            logger.error(&format!(
+                "Skipping unexpected non-output node {} under root",
+                output.id
+            ));
+            continue;
+        }
+
+        if let Some(name) = &output.name {
+            if config.output_blocklist.contains(name) {
+                continue;
+            }
+        }
+
+        let Some(&min_window_width) = config.min_window_width.get(&(output.rect.width as u32))
+        else {
+            continue;
+        };
+
+        for workspace in &output.nodes {
+            if !matches!(workspace.node_type, NodeType::Workspace) {
+                continue;
+            }
+
+            // Honor the same `config.workspace` restriction the
+            // aspect-ratio path is gated on, rather than resplitting
+            // workspaces the user explicitly excluded.
+            if !config.workspace.is_empty() {
+                let in_scope = workspace
+                    .name
+                    .as_deref()
+                    .map_or(false, |name| config.workspace.iter().any(|ws| ws == name));
+                if !in_scope {
+                    continue;
+                }
+            }
+
+            let mut containers = vec![workspace];
+            containers.extend(containers_under(workspace));
+
+            for container in containers {
+                let estimated_width = container.rect.width as f64 / 2.0;
+
+                let Some(cmd) =
+                    min_width_command(container.layout, estimated_width, min_window_width as f64)
+                else {
+                    continue;
                 };
-                conn.run_command(cmd).await?;
+
+                conn.run_command(format!("[con_id={}] {}", container.id, cmd))
+                    .await?;
                 logger.info(&format!(
-                    "Focus changed -> Next split direction set to '{}'",
+                    "Container {} -> pixel budget on output '{}' set layout to '{}'",
+                    container.id,
+                    output.name.as_deref().unwrap_or("?"),
                     cmd
                 ));
             }
         }
     }
+
     Ok(())
 }
 
+/// Decide whether a container's layout needs to flip to respect
+/// `min_window_width`, given its estimated (post-split) child width.
+/// Returns `None` when the container is already in the right layout.
+fn min_width_command(
+    layout: NodeLayout,
+    estimated_width: f64,
+    min_window_width: f64,
+) -> Option<&'static str> {
+    if layout == NodeLayout::SplitH && estimated_width <= min_window_width {
+        Some("splitv")
+    } else if layout == NodeLayout::SplitV && estimated_width > min_window_width {
+        Some("splith")
+    } else {
+        None
+    }
+}
+
+/// Collect every `Workspace`/`Con` descendant of `node`, recursively.
+fn containers_under(node: &Node) -> Vec<&Node> {
+    let mut containers = Vec::new();
+    for child in &node.nodes {
+        if matches!(child.node_type, NodeType::Workspace | NodeType::Con) {
+            containers.push(child);
+        }
+        containers.extend(containers_under(child));
+    }
+    containers
+}
+
+/// Find the first configured rule that matches the given node, if any.
+/// A rule matches when every field it sets (`app_id`, `window_class`,
+/// `title`) matches the node; unset fields are ignored. `title` regexes are
+/// precompiled by `Config::load`, so no parsing happens here.
+fn matching_rule<'a>(
+    node: &Node,
+    rules: &'a [config::ResolvedRule],
+) -> Option<&'a config::ResolvedRule> {
+    rules.iter().find(|rule| {
+        let app_id_matches = rule
+            .app_id
+            .as_deref()
+            .map_or(true, |app_id| node.app_id.as_deref() == Some(app_id));
+
+        let window_class_matches = rule.window_class.as_deref().map_or(true, |class| {
+            node.window_properties
+                .as_ref()
+                .and_then(|props| props.class.as_deref())
+                == Some(class)
+        });
+
+        let title_matches = rule.title.as_ref().map_or(true, |re| {
+            node.name.as_deref().map_or(false, |name| re.is_match(name))
+        });
+
+        app_id_matches && window_class_matches && title_matches
+    })
+}
+
+/// Walk up the tree from `id`, testing each ancestor with `test`. Returns
+/// true as soon as one matches. The parent of a node is found via its
+/// `focus` list, which names the id of the child currently in focus.
+fn any_ancestor(tree: &Node, id: i64, test: impl FnMut(&Node) -> bool) -> bool {
+    find_ancestor(tree, id, test).is_some()
+}
+
+/// Like [`any_ancestor`], but returns the first matching ancestor itself
+/// rather than just whether one exists.
+fn find_ancestor(tree: &Node, id: i64, mut test: impl FnMut(&Node) -> bool) -> Option<&Node> {
+    let mut current = id;
+    while let Some(parent) = tree.find_as_ref(|n| n.focus.contains(&current)) {
+        if test(parent) {
+            return Some(parent);
+        }
+        current = parent.id;
+    }
+    None
+}
+
 /// Check if a node should be skipped (using more robust checks).
-fn should_skip(node: &Node) -> bool {
-    // A more reliable way to detect fullscreen is checking the `percent`.
-    let is_fullscreen = node.percent.map_or(false, |p| p > 1.0);
+///
+/// Rather than trusting the focused node's own `percent`/`layout` fields,
+/// which miss containers that are fullscreen or tabbed/stacked several
+/// layers up, this walks `node` itself and every ancestor of it in `tree`.
+fn should_skip(tree: &Node, node: &Node) -> bool {
     let is_floating = matches!(node.node_type, NodeType::FloatingCon);
-    matches!(node.layout, NodeLayout::Tabbed | NodeLayout::Stacked) || is_fullscreen || is_floating
+
+    let is_fullscreen_node = |n: &Node| {
+        matches!(n.node_type, NodeType::Con | NodeType::FloatingCon) && n.fullscreen_mode != Some(0)
+    };
+    let is_fullscreen = is_fullscreen_node(node) || any_ancestor(tree, node.id, is_fullscreen_node);
+
+    let in_tabbed_or_stacked = any_ancestor(tree, node.id, |n| {
+        matches!(n.layout, NodeLayout::Tabbed | NodeLayout::Stacked)
+    });
+
+    is_floating || is_fullscreen || in_tabbed_or_stacked
 }
 
 /// Get the name of the currently focused workspace.
@@ -185,3 +453,173 @@ async fn get_focused_workspace_name(conn: &mut Connection) -> Result<Option<Stri
         .find(|ws| ws.focused)
         .map(|ws| ws.name))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Build a `Node` from a JSON object, filling in every field sway's IPC
+    /// always sends with an inert default; `overrides` wins. `Node` is
+    /// `#[non_exhaustive]`, so round-tripping through its own `Deserialize`
+    /// impl is the only way to construct one outside of `swayipc-types`.
+    fn node(overrides: serde_json::Value) -> Node {
+        let rect = json!({"x": 0, "y": 0, "width": 0, "height": 0});
+        let mut value = json!({
+            "id": 0,
+            "name": null,
+            "type": "con",
+            "border": "normal",
+            "current_border_width": 0,
+            "layout": "splith",
+            "percent": null,
+            "rect": rect,
+            "window_rect": rect,
+            "deco_rect": rect,
+            "geometry": rect,
+            "urgent": false,
+            "focused": false,
+            "focus": [],
+            "floating": null,
+            "nodes": [],
+            "floating_nodes": [],
+            "sticky": false,
+            "representation": null,
+            "fullscreen_mode": 0,
+            "scratchpad_state": null,
+            "app_id": null,
+            "pid": null,
+            "window": null,
+            "num": null,
+            "window_properties": null,
+            "marks": [],
+            "inhibit_idle": null,
+            "idle_inhibitors": null,
+            "sandbox_engine": null,
+            "sandbox_app_id": null,
+            "sandbox_instance_id": null,
+            "tag": null,
+            "shell": null,
+            "foreign_toplevel_identifier": null,
+            "visible": null,
+            "output": null,
+        });
+        if let serde_json::Value::Object(overrides) = overrides {
+            for (key, v) in overrides {
+                value[key] = v;
+            }
+        }
+        serde_json::from_value(value).expect("well-formed test node")
+    }
+
+    #[test]
+    fn should_skip_fullscreens_the_focused_window_itself() {
+        // `$mod+f` on a single focused window sets `fullscreen_mode` on that
+        // window's own container, not on an ancestor.
+        let focused = node(json!({"id": 2, "focused": true, "fullscreen_mode": 1}));
+        let tree = node(json!({"id": 1, "type": "workspace", "focus": [2], "nodes": [focused]}));
+        let focused = &tree.nodes[0];
+
+        assert!(should_skip(&tree, focused));
+    }
+
+    #[test]
+    fn should_skip_ignores_an_ordinary_window() {
+        let focused = node(json!({"id": 2, "focused": true, "fullscreen_mode": 0}));
+        let tree = node(json!({"id": 1, "type": "workspace", "focus": [2], "nodes": [focused]}));
+        let focused = &tree.nodes[0];
+
+        assert!(!should_skip(&tree, focused));
+    }
+
+    #[test]
+    fn find_ancestor_walks_past_the_starting_node() {
+        let grandchild = node(json!({"id": 3, "focus": []}));
+        let child = node(json!({"id": 2, "layout": "tabbed", "focus": [3], "nodes": [grandchild]}));
+        let tree = node(json!({"id": 1, "focus": [2], "nodes": [child]}));
+
+        let grandchild = &tree.nodes[0].nodes[0];
+        let ancestor = find_ancestor(&tree, grandchild.id, |n| {
+            matches!(n.layout, NodeLayout::Tabbed)
+        });
+
+        assert_eq!(ancestor.map(|n| n.id), Some(2));
+    }
+
+    #[test]
+    fn matching_rule_requires_every_set_field_to_match() {
+        let rules = vec![config::ResolvedRule {
+            app_id: Some("firefox".into()),
+            window_class: None,
+            title: Some(Regex::new("^Mozilla").unwrap()),
+            layout: config::RuleLayout::Tabbed,
+        }];
+
+        let matching = node(json!({"app_id": "firefox", "name": "Mozilla Firefox"}));
+        assert!(matching_rule(&matching, &rules).is_some());
+
+        let wrong_title = node(json!({"app_id": "firefox", "name": "something else"}));
+        assert!(matching_rule(&wrong_title, &rules).is_none());
+
+        let wrong_app = node(json!({"app_id": "kitty", "name": "Mozilla Firefox"}));
+        assert!(matching_rule(&wrong_app, &rules).is_none());
+    }
+
+    #[test]
+    fn decide_focused_layout_suppresses_a_matching_rule_on_a_blocklisted_output() {
+        let focused = node(json!({"id": 3, "focused": true, "app_id": "code"}));
+        let parent = node(json!({"id": 2, "layout": "splith", "focus": [3], "nodes": [focused]}));
+        let workspace = node(
+            json!({"id": 4, "type": "workspace", "focus": [2], "nodes": [parent]}),
+        );
+        let output = node(json!({
+            "id": 5, "type": "output", "name": "eDP-1", "focus": [4], "nodes": [workspace]
+        }));
+        let tree = node(json!({"id": 1, "focus": [5], "nodes": [output]}));
+
+        let focused = &tree.nodes[0].nodes[0].nodes[0];
+
+        let config = Config {
+            ratio: 1.0,
+            workspace: Vec::new(),
+            output_blocklist: vec!["eDP-1".into()],
+            output_ratio: std::collections::HashMap::new(),
+            mode: Mode::AspectRatio,
+            min_window_width: std::collections::HashMap::new(),
+            rules: vec![config::ResolvedRule {
+                app_id: Some("code".into()),
+                window_class: None,
+                title: None,
+                layout: config::RuleLayout::Tabbed,
+            }],
+            quiet: false,
+        };
+
+        // The rule would otherwise force `tabbed` (differing from the
+        // parent's current `splith`), but the output is blocklisted, so the
+        // rule must never get a chance to fire.
+        assert!(decide_focused_layout(&tree, focused, NodeLayout::SplitH, &config).is_none());
+    }
+
+    #[test]
+    fn min_width_command_splits_vertically_once_too_narrow() {
+        assert_eq!(
+            min_width_command(NodeLayout::SplitH, 400.0, 500.0),
+            Some("splitv")
+        );
+    }
+
+    #[test]
+    fn min_width_command_reverts_once_wide_enough_again() {
+        assert_eq!(
+            min_width_command(NodeLayout::SplitV, 600.0, 500.0),
+            Some("splith")
+        );
+    }
+
+    #[test]
+    fn min_width_command_leaves_an_already_correct_layout_alone() {
+        assert_eq!(min_width_command(NodeLayout::SplitH, 600.0, 500.0), None);
+        assert_eq!(min_width_command(NodeLayout::SplitV, 400.0, 500.0), None);
+    }
+}